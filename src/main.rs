@@ -1,4 +1,5 @@
 use clap::{App, Arg};
+use ffmpeg::software::scaling::{context::Context as ScalingContext, flag::Flags as ScalingFlags};
 use ffmpeg::{format, frame, media::Type};
 use plotters::prelude::*;
 
@@ -7,6 +8,32 @@ struct CliOptions {
   output_path: Option<String>,
   output_size: Resolution,
   bpp: bool, // bit per pixel
+  bitrate: bool,
+  window: f64, // seconds, sliding window for --bitrate
+  scenecut: bool,
+  sc_threshold: f64,
+  sc_downscale: u32,
+  sc_pix_format: Option<format::Pixel>,
+  gop: bool,
+  format: Option<String>,
+  target: bool,
+  target_kbps: Option<f64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OutputFormat {
+  Png,
+  Svg,
+  Csv,
+  Json,
+}
+
+// Options controlling --scenecut detection. Built from CliOptions only when
+// --scenecut is set, so get_video_info always gets a parsed pixel format.
+struct SceneCutOptions {
+  threshold: f64,
+  downscale_height: u32,
+  pix_format: format::Pixel,
 }
 
 struct Resolution {
@@ -14,13 +41,53 @@ struct Resolution {
   h: u32,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FrameType {
+  I,
+  P,
+  B,
+  Other,
+}
+
+impl FrameType {
+  fn from_picture_type(t: frame::video::PictureType) -> FrameType {
+    use frame::video::PictureType::*;
+    match t {
+      I => FrameType::I,
+      P => FrameType::P,
+      B => FrameType::B,
+      _ => FrameType::Other,
+    }
+  }
+
+  fn label(&self) -> &'static str {
+    match self {
+      FrameType::I => "I",
+      FrameType::P => "P",
+      FrameType::B => "B",
+      FrameType::Other => "other",
+    }
+  }
+}
+
+struct FrameInfo {
+  size: f64,
+  pts: f64, // seconds: the frame's pts scaled by the stream's time_base
+  frame_type: FrameType,
+  keyframe: bool,
+  scene_cut: bool,
+}
+
 struct VideoInfo {
   w: u32,
   h: u32,
-  bits: Vec<f64>,
+  frames: Vec<FrameInfo>,
 }
 
-fn get_video_info<P: AsRef<str>>(input_path: P) -> Result<VideoInfo, String> {
+fn get_video_info<P: AsRef<str>>(
+  input_path: P,
+  scenecut: Option<&SceneCutOptions>,
+) -> Result<VideoInfo, String> {
   ffmpeg::init().map_err(|e| e.to_string())?;
   let mut ictx = format::input(&input_path).map_err(|e| e.to_string())?;
   let input = ictx
@@ -34,42 +101,260 @@ fn get_video_info<P: AsRef<str>>(input_path: P) -> Result<VideoInfo, String> {
 
   let mut decoded_frame = frame::Video::empty();
   let mut packets = ictx.packets();
-  let mut v_info = VideoInfo { w: 0, h: 0, bits: Vec::new() };
+  let mut w = 0;
+  let mut h = 0;
+  // Tracks the last known pts and the gap between the last two frames, so a
+  // frame with no usable timestamp can still be placed on the timeline.
+  let mut last_pts: Option<f64> = None;
+  let mut last_gap = 0.0;
+  // Scaler, lazily created once we know the source frame size.
+  let mut scaler: Option<ScalingContext> = None;
+  // Each decoded frame paired with its downscaled luma plane (when
+  // --scenecut is on). decoder.decode() can hand frames back in decode
+  // order rather than display order on streams with B-frames, so these are
+  // sorted into presentation order by pts below before anything compares
+  // adjacent frames.
+  let mut pending: Vec<(FrameInfo, Option<Vec<u8>>)> = Vec::new();
   while let Some(Ok((stream, packet))) = packets.next() {
     if stream.index() == input_stream_idx {
       let res = decoder.decode(&packet, &mut decoded_frame);
-      if v_info.w == 0 && v_info.h == 0 {
-        v_info.w = decoded_frame.width();
-        v_info.h = decoded_frame.height();
+      if w == 0 && h == 0 {
+        w = decoded_frame.width();
+        h = decoded_frame.height();
       }
 
       if res.is_ok() {
-        let bit = decoded_frame.packet().size as f64;
-        v_info.bits.push(bit);
+        let time_base = stream.time_base();
+        let tb_secs = time_base.numerator() as f64 / time_base.denominator() as f64;
+        // Use the decoded frame's own timestamp, not the just-fed packet's:
+        // packets are consumed in decode order, but decode() can emit the
+        // frame for an earlier packet once B-frame reordering is involved.
+        let pts = match decoded_frame.pts() {
+          Some(ticks) => ticks as f64 * tb_secs,
+          None if packet.duration() > 0 => {
+            last_pts.unwrap_or(0.0) + packet.duration() as f64 * tb_secs
+          }
+          None => last_pts.unwrap_or(0.0) + last_gap,
+        };
+        last_gap = last_pts.map(|p| pts - p).unwrap_or(last_gap);
+        last_pts = Some(pts);
+
+        let luma = match scenecut {
+          Some(opts) => Some(downscale_luma(&decoded_frame, opts, &mut scaler).map_err(|e| e.to_string())?),
+          None => None,
+        };
+
+        let size = decoded_frame.packet().size as f64;
+        pending.push((
+          FrameInfo {
+            size,
+            pts,
+            frame_type: FrameType::from_picture_type(decoded_frame.picture_type()),
+            keyframe: decoded_frame.is_key(),
+            scene_cut: false,
+          },
+          luma,
+        ));
       }
     }
   }
 
-  Ok(v_info)
+  pending.sort_by(|a, b| a.0.pts.partial_cmp(&b.0.pts).unwrap());
+
+  // Now that frames are in presentation order, compare each against the one
+  // immediately before it on the timeline instead of the one decode() most
+  // recently handed back.
+  if let Some(opts) = scenecut {
+    let mut prev_luma: Option<Vec<u8>> = None;
+    for (frame, luma) in pending.iter_mut() {
+      if let Some(luma) = luma {
+        frame.scene_cut = match prev_luma.as_ref() {
+          Some(prev) if prev.len() == luma.len() => {
+            luma_diff_exceeds_threshold(prev, luma, opts.threshold)
+          }
+          _ => false,
+        };
+        prev_luma = Some(luma.clone());
+      }
+    }
+  }
+
+  let frames = pending.into_iter().map(|(f, _)| f).collect();
+  Ok(VideoInfo { w, h, frames })
+}
+
+// Downscales `decoded` to `opts.downscale_height` in `opts.pix_format` and
+// returns its luma plane. The scene-cut comparison itself happens afterward,
+// once all frames are sorted into presentation order (see get_video_info).
+fn downscale_luma(
+  decoded: &frame::Video,
+  opts: &SceneCutOptions,
+  scaler: &mut Option<ScalingContext>,
+) -> Result<Vec<u8>, String> {
+  let scaled_h = opts.downscale_height;
+  let scaled_w = ((decoded.width() as u64 * scaled_h as u64) / decoded.height() as u64) as u32;
+
+  if scaler.is_none() {
+    *scaler = Some(
+      ScalingContext::get(
+        decoded.format(),
+        decoded.width(),
+        decoded.height(),
+        opts.pix_format,
+        scaled_w,
+        scaled_h,
+        ScalingFlags::BILINEAR,
+      )
+      .map_err(|e| e.to_string())?,
+    );
+  }
+
+  let mut scaled = frame::Video::empty();
+  scaler.as_mut().unwrap().run(decoded, &mut scaled).map_err(|e| e.to_string())?;
+  Ok(scaled.data(0).to_vec())
+}
+
+// Normalized mean absolute difference between two equal-length luma planes,
+// compared against `threshold`. Kept separate from downscale_luma so the
+// threshold math can be unit tested without decoding a real frame.
+fn luma_diff_exceeds_threshold(prev: &[u8], luma: &[u8], threshold: f64) -> bool {
+  let diff: u64 = luma
+    .iter()
+    .zip(prev.iter())
+    .map(|(a, b)| (*a as i32 - *b as i32).unsigned_abs() as u64)
+    .sum();
+  let normalized = diff as f64 / (luma.len() as f64 * 255.0);
+  normalized > threshold
+}
+
+// Prints a count/average-size breakdown for each observed frame type.
+fn print_frame_type_summary(frames: &[FrameInfo]) {
+  for frame_type in [FrameType::I, FrameType::P, FrameType::B, FrameType::Other] {
+    let sizes: Vec<f64> = frames
+      .iter()
+      .filter(|f| f.frame_type == frame_type)
+      .map(|f| f.size)
+      .collect();
+    if sizes.is_empty() {
+      continue;
+    }
+    let avg = sizes.iter().sum::<f64>() / sizes.len() as f64;
+    println!(
+      "{}: {} frames, avg {:.1}",
+      frame_type.label(),
+      sizes.len(),
+      avg
+    );
+  }
+}
+
+// Prints the frame numbers detected as scene cuts, if any.
+fn print_scene_cuts(frames: &[FrameInfo]) {
+  let cuts: Vec<String> = frames
+    .iter()
+    .enumerate()
+    .filter(|(_, f)| f.scene_cut)
+    .map(|(i, _)| i.to_string())
+    .collect();
+  if !cuts.is_empty() {
+    println!("Scene cuts at frames: {}", cuts.join(", "));
+  }
+}
+
+struct Gop {
+  start: usize,
+  length: usize,
+  total_bytes: f64,
+  peak_frame: usize,
+}
+
+// Groups frames into GOPs, each starting at a keyframe (the very first frame
+// starts a GOP even if it isn't flagged as a keyframe).
+fn compute_gops(frames: &[FrameInfo]) -> Vec<Gop> {
+  let mut starts: Vec<usize> = frames
+    .iter()
+    .enumerate()
+    .filter(|(i, f)| *i == 0 || f.keyframe)
+    .map(|(i, _)| i)
+    .collect();
+  if starts.is_empty() {
+    starts.push(0);
+  }
+
+  starts
+    .iter()
+    .enumerate()
+    .map(|(gi, &start)| {
+      let end = starts.get(gi + 1).copied().unwrap_or(frames.len());
+      let slice = &frames[start..end];
+      let total_bytes: f64 = slice.iter().map(|f| f.size).sum();
+      let peak_offset = slice
+        .iter()
+        .enumerate()
+        .max_by(|(_, a), (_, b)| a.size.partial_cmp(&b.size).unwrap())
+        .map(|(i, _)| i)
+        .unwrap_or(0);
+      Gop { start, length: slice.len(), total_bytes, peak_frame: start + peak_offset }
+    })
+    .collect()
+}
+
+// Prints a start/length/total/peak-frame table, one row per GOP. `bpp`
+// controls whether the total column is labeled in bytes or in the
+// per-pixel unit --bpp rescales FrameInfo.size into.
+fn print_gop_table(gops: &[Gop], bpp: bool) {
+  let total_label = if bpp { "total_bpp" } else { "total_bytes" };
+  println!("{:>8} {:>8} {:>12} {:>10}", "start", "length", total_label, "peak_frame");
+  for gop in gops {
+    println!(
+      "{:>8} {:>8} {:>12.1} {:>10}",
+      gop.start, gop.length, gop.total_bytes, gop.peak_frame
+    );
+  }
 }
 
 fn draw_graph<P: AsRef<std::path::Path>>(
-  datas: &[f64],
+  frames: &[FrameInfo],
+  gops: &[Gop],
   y_label: &str,
+  bpp: bool,
   output_size: Resolution,
+  format: OutputFormat,
   output_path: P,
 ) -> Result<(), Box<dyn std::error::Error>> {
-  let root = BitMapBackend::new(&output_path, (output_size.w, output_size.h))
-    .into_drawing_area();
+  match format {
+    OutputFormat::Svg => {
+      let root = SVGBackend::new(&output_path, (output_size.w, output_size.h))
+        .into_drawing_area();
+      draw_graph_on(root, frames, gops, y_label, bpp)
+    }
+    _ => {
+      let root = BitMapBackend::new(&output_path, (output_size.w, output_size.h))
+        .into_drawing_area();
+      draw_graph_on(root, frames, gops, y_label, bpp)
+    }
+  }
+}
+
+fn draw_graph_on<DB: DrawingBackend>(
+  root: DrawingArea<DB, plotters::coord::Shift>,
+  frames: &[FrameInfo],
+  gops: &[Gop],
+  y_label: &str,
+  bpp: bool,
+) -> Result<(), Box<dyn std::error::Error>>
+where
+  DB::ErrorType: 'static,
+{
   root.fill(&WHITE)?;
 
-  let max = datas.iter().fold(f64::NAN, |m, v| v.max(m)) as f64;
-  let avg = datas.iter().sum::<f64>() / datas.len() as f64;
+  let max = frames.iter().map(|f| f.size).fold(f64::NAN, |m, v| v.max(m));
+  let avg = frames.iter().map(|f| f.size).sum::<f64>() / frames.len() as f64;
 
   let mut chart = ChartBuilder::on(&root)
     .set_label_area_size(LabelAreaPosition::Left, (10).percent_width())
     .set_label_area_size(LabelAreaPosition::Bottom, (10).percent_height())
-    .build_cartesian_2d(0..(datas.len() - 1), 0.0..max * 1.2)?;
+    .build_cartesian_2d(0..(frames.len() - 1), 0.0..max * 1.2)?;
   chart
     .configure_mesh()
     .disable_x_mesh()
@@ -79,17 +364,272 @@ fn draw_graph<P: AsRef<std::path::Path>>(
     .label_style(("san-serif", (3).percent_height()))
     .draw()?;
 
-  chart.draw_series(LineSeries::new(
-    (0..).zip(datas.iter()).map(|(x, y)| (x, *y as f64)),
+  // Keyframes get their own series so GOP boundaries stand out from the
+  // ordinary P/B scatter.
+  chart.draw_series(PointSeries::of_element(
+    frames
+      .iter()
+      .enumerate()
+      .filter(|(_, f)| f.keyframe)
+      .map(|(x, f)| (x, f.size)),
+    3,
+    &GREEN,
+    &|c, s, st| Circle::new(c, s, st.filled()),
+  ))?;
+
+  chart.draw_series(PointSeries::of_element(
+    frames
+      .iter()
+      .enumerate()
+      .filter(|(_, f)| !f.keyframe && f.frame_type == FrameType::P)
+      .map(|(x, f)| (x, f.size)),
+    2,
     &BLUE.mix(0.8),
+    &|c, s, st| Circle::new(c, s, st.filled()),
+  ))?;
+
+  chart.draw_series(PointSeries::of_element(
+    frames
+      .iter()
+      .enumerate()
+      .filter(|(_, f)| !f.keyframe && f.frame_type == FrameType::B)
+      .map(|(x, f)| (x, f.size)),
+    2,
+    &CYAN.mix(0.8),
+    &|c, s, st| Circle::new(c, s, st.filled()),
+  ))?;
+
+  // Non-IDR I-frames and anything else ffmpeg didn't classify as I/P/B still
+  // count toward the averages and GOP bytes, so they need a series too or
+  // they'd silently vanish from the breakdown.
+  chart.draw_series(PointSeries::of_element(
+    frames
+      .iter()
+      .enumerate()
+      .filter(|(_, f)| {
+        !f.keyframe && (f.frame_type == FrameType::I || f.frame_type == FrameType::Other)
+      })
+      .map(|(x, f)| (x, f.size)),
+    2,
+    &YELLOW.mix(0.8),
+    &|c, s, st| Circle::new(c, s, st.filled()),
   ))?;
 
   // Draw average bit
   chart.draw_series(LineSeries::new(
-    (0..datas.len()).map(|x| (x, avg as f64)),
+    (0..frames.len()).map(|x| (x, avg)),
     &RED.mix(0.3),
   ))?;
 
+  for (x, _) in frames.iter().enumerate().filter(|(_, f)| f.scene_cut) {
+    chart.draw_series(LineSeries::new(vec![(x, 0.0), (x, max * 1.2)], &MAGENTA))?;
+  }
+
+  // Per-GOP average size, as a step function on top of the per-frame series.
+  for gop in gops {
+    let end = gop.start + gop.length - 1;
+    let gop_avg = frames[gop.start..=end].iter().map(|f| f.size).sum::<f64>() / gop.length as f64;
+    chart.draw_series(LineSeries::new(vec![(gop.start, gop_avg), (end, gop_avg)], &BLACK))?;
+  }
+
+  print_frame_type_summary(frames);
+  print_scene_cuts(frames);
+  if !gops.is_empty() {
+    print_gop_table(gops, bpp);
+  }
+
+  Ok(())
+}
+
+// Sums packet bits within a trailing `window_secs` slice of the timeline and
+// expresses the result in kbps, one point per decoded frame.
+fn compute_windowed_bitrate_kbps(frames: &[FrameInfo], window_secs: f64) -> Vec<(f64, f64)> {
+  frames
+    .iter()
+    .enumerate()
+    .map(|(i, frame)| {
+      let window_start = frame.pts - window_secs;
+      let bits: f64 = frames[..=i]
+        .iter()
+        .rev()
+        .take_while(|f| f.pts > window_start)
+        .map(|f| f.size * 8.0)
+        .sum();
+      (frame.pts, bits / window_secs / 1000.0)
+    })
+    .collect()
+}
+
+// Built-in resolution -> "expected" bitrate (kbps) table, used by --target
+// when no explicit kbps override is given. Interpolated linearly by pixel
+// count between entries, and capped at the last entry beyond 4K.
+const TARGET_BITRATE_TABLE: [(u32, u32, f64); 5] = [
+  (640, 360, 500.0),
+  (1280, 720, 1000.0),
+  (1920, 1080, 2000.0),
+  (2560, 1440, 3000.0),
+  (3840, 2160, 4000.0),
+];
+
+fn target_bitrate_kbps(w: u32, h: u32) -> f64 {
+  let pixels = w as f64 * h as f64;
+  let first = TARGET_BITRATE_TABLE[0];
+  if pixels <= first.0 as f64 * first.1 as f64 {
+    return first.2;
+  }
+  for pair in TARGET_BITRATE_TABLE.windows(2) {
+    let (w0, h0, b0) = pair[0];
+    let (w1, h1, b1) = pair[1];
+    let p0 = w0 as f64 * h0 as f64;
+    let p1 = w1 as f64 * h1 as f64;
+    if pixels <= p1 {
+      let t = (pixels - p0) / (p1 - p0);
+      return b0 + t * (b1 - b0);
+    }
+  }
+  TARGET_BITRATE_TABLE.last().unwrap().2
+}
+
+// Picks the export format from an explicit --format flag, falling back to
+// the output path's extension.
+fn output_format<P: AsRef<str>>(
+  output_path: P,
+  explicit: Option<&str>,
+) -> Result<OutputFormat, String> {
+  if let Some(f) = explicit {
+    return match f {
+      "csv" => Ok(OutputFormat::Csv),
+      "json" => Ok(OutputFormat::Json),
+      "png" => Ok(OutputFormat::Png),
+      "svg" => Ok(OutputFormat::Svg),
+      other => Err(format!("Unknown output format: {}", other)),
+    };
+  }
+  match std::path::Path::new(output_path.as_ref())
+    .extension()
+    .and_then(|e| e.to_str())
+  {
+    Some("csv") => Ok(OutputFormat::Csv),
+    Some("json") => Ok(OutputFormat::Json),
+    Some("svg") => Ok(OutputFormat::Svg),
+    _ => Ok(OutputFormat::Png),
+  }
+}
+
+// Writes one row per frame: index, pts, size, windowed bitrate, frame type
+// and whether it was flagged as a scene cut.
+fn write_csv<P: AsRef<std::path::Path>>(
+  frames: &[FrameInfo],
+  window_secs: f64,
+  output_path: P,
+) -> Result<(), std::io::Error> {
+  use std::io::Write;
+  let bitrate = compute_windowed_bitrate_kbps(frames, window_secs);
+  let mut file = std::fs::File::create(output_path)?;
+  writeln!(file, "frame,pts,size_bytes,bitrate_kbps,frame_type,scene_cut")?;
+  for (i, f) in frames.iter().enumerate() {
+    writeln!(
+      file,
+      "{},{},{},{:.1},{},{}",
+      i,
+      f.pts,
+      f.size,
+      bitrate[i].1,
+      f.frame_type.label(),
+      f.scene_cut
+    )?;
+  }
+  Ok(())
+}
+
+// Writes the same per-frame series as write_csv, plus the clip's resolution.
+fn write_json<P: AsRef<std::path::Path>>(
+  v_info: &VideoInfo,
+  window_secs: f64,
+  output_path: P,
+) -> Result<(), std::io::Error> {
+  use std::io::Write;
+  let bitrate = compute_windowed_bitrate_kbps(&v_info.frames, window_secs);
+  let mut file = std::fs::File::create(output_path)?;
+  writeln!(file, "{{")?;
+  writeln!(file, "  \"width\": {},", v_info.w)?;
+  writeln!(file, "  \"height\": {},", v_info.h)?;
+  writeln!(file, "  \"frames\": [")?;
+  for (i, f) in v_info.frames.iter().enumerate() {
+    let comma = if i + 1 == v_info.frames.len() { "" } else { "," };
+    writeln!(
+      file,
+      "    {{ \"frame\": {}, \"pts\": {}, \"size_bytes\": {}, \"bitrate_kbps\": {:.1}, \"frame_type\": \"{}\", \"scene_cut\": {} }}{}",
+      i, f.pts, f.size, bitrate[i].1, f.frame_type.label(), f.scene_cut, comma
+    )?;
+  }
+  writeln!(file, "  ]")?;
+  writeln!(file, "}}")?;
+  Ok(())
+}
+
+fn draw_bitrate_graph<P: AsRef<std::path::Path>>(
+  series: &[(f64, f64)],
+  scene_cuts: &[f64],
+  target_kbps: Option<f64>,
+  output_size: Resolution,
+  format: OutputFormat,
+  output_path: P,
+) -> Result<(), Box<dyn std::error::Error>> {
+  match format {
+    OutputFormat::Svg => {
+      let root = SVGBackend::new(&output_path, (output_size.w, output_size.h))
+        .into_drawing_area();
+      draw_bitrate_graph_on(root, series, scene_cuts, target_kbps)
+    }
+    _ => {
+      let root = BitMapBackend::new(&output_path, (output_size.w, output_size.h))
+        .into_drawing_area();
+      draw_bitrate_graph_on(root, series, scene_cuts, target_kbps)
+    }
+  }
+}
+
+fn draw_bitrate_graph_on<DB: DrawingBackend>(
+  root: DrawingArea<DB, plotters::coord::Shift>,
+  series: &[(f64, f64)],
+  scene_cuts: &[f64],
+  target_kbps: Option<f64>,
+) -> Result<(), Box<dyn std::error::Error>>
+where
+  DB::ErrorType: 'static,
+{
+  root.fill(&WHITE)?;
+
+  let max_x = series.iter().map(|(x, _)| *x).fold(f64::NAN, |m, v| v.max(m));
+  let max_y = series
+    .iter()
+    .map(|(_, y)| *y)
+    .fold(target_kbps.unwrap_or(0.0), |m, v| v.max(m));
+
+  let mut chart = ChartBuilder::on(&root)
+    .set_label_area_size(LabelAreaPosition::Left, (10).percent_width())
+    .set_label_area_size(LabelAreaPosition::Bottom, (10).percent_height())
+    .build_cartesian_2d(0.0..max_x, 0.0..max_y * 1.2)?;
+  chart
+    .configure_mesh()
+    .disable_x_mesh()
+    .disable_y_mesh()
+    .y_desc("kbps")
+    .x_desc("Time (s)")
+    .label_style(("san-serif", (3).percent_height()))
+    .draw()?;
+
+  chart.draw_series(LineSeries::new(series.iter().cloned(), &BLUE.mix(0.8)))?;
+
+  for &x in scene_cuts {
+    chart.draw_series(LineSeries::new(vec![(x, 0.0), (x, max_y * 1.2)], &MAGENTA))?;
+  }
+
+  if let Some(target) = target_kbps {
+    chart.draw_series(LineSeries::new(vec![(0.0, target), (max_x, target)], &BLACK))?;
+  }
+
   Ok(())
 }
 
@@ -124,8 +664,73 @@ fn parse_cli() -> Result<CliOptions, String> {
     .arg(
       Arg::with_name("bit_per_pixel")
         .long("bpp")
+        .conflicts_with("bitrate")
         .help("Sets to output bit per pixel"),
     )
+    .arg(
+      Arg::with_name("bitrate")
+        .long("bitrate")
+        .conflicts_with("bit_per_pixel")
+        .help("Sets to output windowed bitrate (kbps) over time"),
+    )
+    .arg(
+      Arg::with_name("window")
+        .long("window")
+        .takes_value(true)
+        .default_value("1.0")
+        .help("Sets the sliding window size in seconds for --bitrate"),
+    )
+    .arg(
+      Arg::with_name("scenecut")
+        .long("scenecut")
+        .help("Sets to detect scene cuts and mark them on the graph"),
+    )
+    .arg(
+      Arg::with_name("sc_threshold")
+        .long("sc-threshold")
+        .takes_value(true)
+        .default_value("0.3")
+        .help("Sets the normalized luma difference threshold for --scenecut"),
+    )
+    .arg(
+      Arg::with_name("sc_downscale")
+        .long("sc-downscale")
+        .takes_value(true)
+        .default_value("270")
+        .help("Sets the downscale height used for --scenecut detection"),
+    )
+    .arg(
+      Arg::with_name("sc_pix_format")
+        .long("sc-pix-format")
+        .takes_value(true)
+        .default_value("gray")
+        .help("Sets the pixel format used for --scenecut detection (ignored unless --scenecut is set)"),
+    )
+    .arg(
+      Arg::with_name("gop")
+        .long("gop")
+        .help("Sets to overlay a per-GOP average frame size step line and print a GOP table (with --bitrate, only the table is printed)"),
+    )
+    .arg(
+      Arg::with_name("format")
+        .long("format")
+        .takes_value(true)
+        .possible_values(&["png", "svg", "csv", "json"])
+        .help("Sets the output format, overriding the one inferred from the output path"),
+    )
+    .arg(
+      Arg::with_name("target")
+        .long("target")
+        .requires("bitrate")
+        .help("Sets to draw a target-bitrate reference line, picked from the clip's resolution (requires --bitrate)"),
+    )
+    .arg(
+      Arg::with_name("target_kbps")
+        .long("target-kbps")
+        .takes_value(true)
+        .requires("bitrate")
+        .help("Sets an explicit target bitrate (kbps), overriding the resolution preset (requires --bitrate)"),
+    )
     .get_matches();
 
   let input_path = cli.value_of("input").map(|s| s.to_owned());
@@ -137,8 +742,59 @@ fn parse_cli() -> Result<CliOptions, String> {
     .collect::<Vec<u32>>();
   let output_size = Resolution { w: output_size[0], h: output_size[1] };
   let bpp = cli.is_present("bit_per_pixel");
+  let bitrate = cli.is_present("bitrate");
+  let window = cli.value_of("window").unwrap().parse::<f64>().map_err(|e| e.to_string())?;
+  if window <= 0.0 {
+    return Err(format!("--window must be greater than 0, got {}", window));
+  }
+  let scenecut = cli.is_present("scenecut");
+  let sc_threshold = cli
+    .value_of("sc_threshold")
+    .unwrap()
+    .parse::<f64>()
+    .map_err(|e| e.to_string())?;
+  let sc_downscale = cli
+    .value_of("sc_downscale")
+    .unwrap()
+    .parse::<u32>()
+    .map_err(|e| e.to_string())?;
+  // Only validated when --scenecut is actually in play, so an invocation
+  // with no scene-cut flags at all can't be broken by this one.
+  let sc_pix_format = if scenecut {
+    Some(
+      cli
+        .value_of("sc_pix_format")
+        .unwrap()
+        .parse::<format::Pixel>()
+        .map_err(|e| e.to_string())?,
+    )
+  } else {
+    None
+  };
+  let gop = cli.is_present("gop");
+  let format = cli.value_of("format").map(|s| s.to_owned());
+  let target = cli.is_present("target");
+  let target_kbps = cli
+    .value_of("target_kbps")
+    .map(|s| s.parse::<f64>().map_err(|e| e.to_string()))
+    .transpose()?;
 
-  Ok(CliOptions { input_path, output_path, output_size, bpp })
+  Ok(CliOptions {
+    input_path,
+    output_path,
+    output_size,
+    bpp,
+    bitrate,
+    window,
+    scenecut,
+    sc_threshold,
+    sc_downscale,
+    sc_pix_format,
+    gop,
+    format,
+    target,
+    target_kbps,
+  })
 }
 
 fn main() -> Result<(), String> {
@@ -147,20 +803,62 @@ fn main() -> Result<(), String> {
   let output_path = cli.output_path.unwrap();
   let output_size = cli.output_size;
   let use_bpp = cli.bpp;
+  let use_bitrate = cli.bitrate;
+  let window = cli.window;
+  let scenecut_opts = if cli.scenecut {
+    Some(SceneCutOptions {
+      threshold: cli.sc_threshold,
+      downscale_height: cli.sc_downscale,
+      pix_format: cli.sc_pix_format.unwrap(),
+    })
+  } else {
+    None
+  };
 
-  let v_info = get_video_info(&input_path)?;
-  let (data, y_label) = if use_bpp {
-    let pix_num = v_info.w * v_info.h;
-    let bpps = v_info
-      .bits
+  let v_info = get_video_info(&input_path, scenecut_opts.as_ref())?;
+
+  let format = output_format(&output_path, cli.format.as_deref())?;
+  match format {
+    OutputFormat::Csv => return write_csv(&v_info.frames, window, &output_path).map_err(|e| e.to_string()),
+    OutputFormat::Json => return write_json(&v_info, window, &output_path).map_err(|e| e.to_string()),
+    OutputFormat::Png | OutputFormat::Svg => {}
+  }
+
+  if use_bitrate {
+    print_frame_type_summary(&v_info.frames);
+    print_scene_cuts(&v_info.frames);
+    if cli.gop {
+      print_gop_table(&compute_gops(&v_info.frames), false);
+    }
+
+    let series = compute_windowed_bitrate_kbps(&v_info.frames, window);
+    let scene_cuts: Vec<f64> = v_info
+      .frames
       .iter()
-      .map(|x| *x as f64 / pix_num as f64)
-      .collect::<Vec<f64>>();
-    (bpps, "bit per pixel")
+      .filter(|f| f.scene_cut)
+      .map(|f| f.pts)
+      .collect();
+    let target_kbps = if cli.target || cli.target_kbps.is_some() {
+      Some(cli.target_kbps.unwrap_or_else(|| target_bitrate_kbps(v_info.w, v_info.h)))
+    } else {
+      None
+    };
+    return draw_bitrate_graph(&series, &scene_cuts, target_kbps, output_size, format, &output_path)
+      .map_err(|err| err.to_string());
+  }
+  let (frames, y_label) = if use_bpp {
+    let pix_num = v_info.w * v_info.h;
+    let frames = v_info
+      .frames
+      .into_iter()
+      .map(|f| FrameInfo { size: f.size / pix_num as f64, ..f })
+      .collect::<Vec<FrameInfo>>();
+    (frames, "bit per pixel")
   } else {
-    (v_info.bits, "bit")
+    (v_info.frames, "bit")
   };
-  draw_graph(&data, y_label, output_size, &output_path)
+  let gops = if cli.gop { compute_gops(&frames) } else { Vec::new() };
+  draw_graph(&frames, &gops, y_label, use_bpp, output_size, format, &output_path)
     .map_err(|err| err.to_string())?;
   Ok(())
 }
@@ -173,10 +871,14 @@ pub mod test {
 
   #[test]
   fn draw_normal_graph() {
-    let datas = [3000.0, 2000.0, 1500.0];
+    let frames = [
+      FrameInfo { size: 3000.0, pts: 0.0, frame_type: FrameType::I, keyframe: true, scene_cut: true },
+      FrameInfo { size: 2000.0, pts: 1.0, frame_type: FrameType::P, keyframe: false, scene_cut: false },
+      FrameInfo { size: 1500.0, pts: 2.0, frame_type: FrameType::B, keyframe: false, scene_cut: false },
+    ];
     let output_size = Resolution { w: 1280, h: 960 };
     let output_path = "./draw_graph_test.png";
-    assert!(draw_graph(&datas, "bit", output_size, output_path).is_ok());
+    assert!(draw_graph(&frames, &[], "bit", false, output_size, OutputFormat::Png, output_path).is_ok());
     assert!(Path::new(output_path).exists());
     assert!(fs::remove_file(output_path).is_ok());
   }
@@ -188,12 +890,132 @@ pub mod test {
     //   -vcodec libx264 -profile:v baseline -pix_fmt yuv420p testsrc_3_frames.mp4
     let input_path = "./test/testsrc_3_frames.mp4";
     let expected = [5068.0, 206.0, 174.0];
-    let v_info = get_video_info(&input_path).unwrap();
+    // Baseline profile disables B-frames, so this clip is a single GOP:
+    // an IDR keyframe followed by two ordinary P-frames.
+    let expected_types = [(FrameType::I, true), (FrameType::P, false), (FrameType::P, false)];
+    let v_info = get_video_info(&input_path, None).unwrap();
 
     assert!(v_info.w == 320 && v_info.h == 180);
-    assert!(v_info.bits.len() == expected.len());
-    for (b, e) in v_info.bits.iter().zip(expected.iter()) {
-      assert!(b == e);
+    assert!(v_info.frames.len() == expected.len());
+    for (f, e) in v_info.frames.iter().zip(expected.iter()) {
+      assert!(f.size == *e);
     }
+    for (f, (frame_type, keyframe)) in v_info.frames.iter().zip(expected_types.iter()) {
+      assert!(f.frame_type == *frame_type);
+      assert!(f.keyframe == *keyframe);
+    }
+  }
+
+  #[test]
+  fn frame_type_from_picture_type_maps_i_p_b_and_none() {
+    use ffmpeg::frame::video::PictureType;
+    assert!(FrameType::from_picture_type(PictureType::I) == FrameType::I);
+    assert!(FrameType::from_picture_type(PictureType::P) == FrameType::P);
+    assert!(FrameType::from_picture_type(PictureType::B) == FrameType::B);
+    assert!(FrameType::from_picture_type(PictureType::None) == FrameType::Other);
+  }
+
+  #[test]
+  fn luma_diff_exceeds_threshold_identical_is_no_cut() {
+    let prev = [10u8, 20, 30, 40];
+    let luma = prev;
+    assert!(!luma_diff_exceeds_threshold(&prev, &luma, 0.0));
+  }
+
+  #[test]
+  fn luma_diff_exceeds_threshold_large_change_trips_threshold() {
+    let prev = [0u8, 0, 0, 0];
+    let luma = [255u8, 255, 255, 255];
+    assert!(luma_diff_exceeds_threshold(&prev, &luma, 0.5));
+  }
+
+  #[test]
+  fn luma_diff_exceeds_threshold_small_change_stays_under_threshold() {
+    let prev = [100u8, 100, 100, 100];
+    let luma = [105u8, 100, 100, 100];
+    assert!(!luma_diff_exceeds_threshold(&prev, &luma, 0.3));
+  }
+
+  fn frame_at(pts: f64, size: f64) -> FrameInfo {
+    FrameInfo { size, pts, frame_type: FrameType::P, keyframe: false, scene_cut: false }
+  }
+
+  fn frame_kf(pts: f64, size: f64, keyframe: bool) -> FrameInfo {
+    FrameInfo { size, pts, frame_type: FrameType::P, keyframe, scene_cut: false }
+  }
+
+  #[test]
+  fn compute_gops_splits_on_keyframes() {
+    let frames = [
+      frame_kf(0.0, 100.0, true),
+      frame_kf(1.0, 50.0, false),
+      frame_kf(2.0, 300.0, false),
+      frame_kf(3.0, 100.0, true),
+      frame_kf(4.0, 20.0, false),
+    ];
+    let gops = compute_gops(&frames);
+    assert!(gops.len() == 2);
+    assert!(gops[0].start == 0 && gops[0].length == 3);
+    assert!((gops[0].total_bytes - 450.0).abs() < 1e-9);
+    assert!(gops[0].peak_frame == 2); // the 300-byte frame within the GOP
+    assert!(gops[1].start == 3 && gops[1].length == 2);
+    assert!((gops[1].total_bytes - 120.0).abs() < 1e-9);
+  }
+
+  #[test]
+  fn compute_gops_treats_first_frame_as_a_gop_start_even_without_keyframe_flag() {
+    let frames = [frame_kf(0.0, 10.0, false), frame_kf(1.0, 20.0, false)];
+    let gops = compute_gops(&frames);
+    assert!(gops.len() == 1);
+    assert!(gops[0].start == 0 && gops[0].length == 2);
+  }
+
+  #[test]
+  fn target_bitrate_kbps_below_first_entry_is_clamped() {
+    assert!((target_bitrate_kbps(320, 180) - 500.0).abs() < 1e-9);
+  }
+
+  #[test]
+  fn target_bitrate_kbps_exact_entries_match_table() {
+    assert!((target_bitrate_kbps(1280, 720) - 1000.0).abs() < 1e-9);
+    assert!((target_bitrate_kbps(1920, 1080) - 2000.0).abs() < 1e-9);
+  }
+
+  #[test]
+  fn target_bitrate_kbps_interpolates_between_entries() {
+    // Halfway (by pixel count) between 720p (1000) and 1080p (2000).
+    let pixels = (1280.0 * 720.0 + 1920.0 * 1080.0) / 2.0;
+    let h = 900u32;
+    let w = (pixels / h as f64).round() as u32;
+    let got = target_bitrate_kbps(w, h);
+    assert!(got > 1000.0 && got < 2000.0);
+  }
+
+  #[test]
+  fn target_bitrate_kbps_above_last_entry_is_capped() {
+    assert!((target_bitrate_kbps(7680, 4320) - 4000.0).abs() < 1e-9);
+  }
+
+  #[test]
+  fn compute_windowed_bitrate_kbps_sums_trailing_window() {
+    // One frame per second, 1000 bytes each; a 2s window should cover the
+    // current frame plus exactly one prior frame.
+    let frames = [frame_at(0.0, 1000.0), frame_at(1.0, 1000.0), frame_at(2.0, 1000.0)];
+    let series = compute_windowed_bitrate_kbps(&frames, 2.0);
+    assert!(series.len() == 3);
+    assert!((series[0].1 - 4.0).abs() < 1e-9); // 1000 * 8 / 2.0 / 1000
+    assert!((series[1].1 - 8.0).abs() < 1e-9); // frames 0+1
+    assert!((series[2].1 - 8.0).abs() < 1e-9); // frames 1+2, frame 0 is out of window
+  }
+
+  #[test]
+  fn compute_windowed_bitrate_kbps_handles_irregular_gaps() {
+    // Mirrors the timeline a duration/gap-based pts fallback would produce:
+    // an uneven gap followed by frames bunched closer together.
+    let frames = [frame_at(0.0, 1000.0), frame_at(3.0, 1000.0), frame_at(3.5, 1000.0)];
+    let series = compute_windowed_bitrate_kbps(&frames, 1.0);
+    assert!((series[0].1 - 8.0).abs() < 1e-9); // only itself within the window
+    assert!((series[1].1 - 8.0).abs() < 1e-9); // the gap pushed frame 0 out of window
+    assert!((series[2].1 - 16.0).abs() < 1e-9); // frames 1+2 both fall within 1s of 3.5
   }
 }